@@ -0,0 +1,206 @@
+use log::{Level, LevelFilter};
+use regex::Regex;
+
+/// A single `target=level` directive, e.g. `myapp::noisy=warn`.
+struct Rule {
+    target_prefix: String,
+    level: LevelFilter,
+}
+
+/// A `RUST_LOG`-style filter: a default level plus a set of per-target
+/// overrides, matched longest-prefix-first, with an optional regex applied
+/// to the rendered message.
+///
+/// Directive syntax: `"myapp=debug,myapp::noisy=warn,off"` — a bare level
+/// with no `=` (anywhere in the list) sets the default instead of adding a
+/// rule, and `off` is shorthand for `LevelFilter::Off`. A trailing
+/// `/pattern` restricts matching records further to ones whose rendered
+/// message matches the regex.
+pub(crate) struct Filter {
+    rules: Vec<Rule>,
+    default: LevelFilter,
+    message_regex: Option<Regex>,
+}
+
+impl Filter {
+    pub fn new(default: LevelFilter) -> Self {
+        Filter {
+            rules: Vec::new(),
+            default,
+            message_regex: None,
+        }
+    }
+
+    /// A filter close to the crate's original heuristic: let records whose
+    /// target starts with `module` through at `default`, and let everything
+    /// else through only at `Warn` or above. Unlike the original substring
+    /// check, `module` must be a prefix of the target. Used when nothing
+    /// more specific has been configured.
+    pub fn default_for_module(module: &str, default: LevelFilter) -> Self {
+        let mut filter = Filter::new(LevelFilter::Warn);
+        filter.rules.push(Rule {
+            target_prefix: module.to_string(),
+            level: default,
+        });
+        filter
+    }
+
+    pub fn parse(spec: &str) -> Self {
+        let (directives, regex) = match spec.split_once('/') {
+            Some((directives, pattern)) => (directives, Regex::new(pattern).ok()),
+            None => (spec, None),
+        };
+
+        let mut rules = Vec::new();
+        let mut default = LevelFilter::Info;
+
+        for directive in directives.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Some(level) = parse_level(level) {
+                        rules.push(Rule {
+                            target_prefix: target.to_string(),
+                            level,
+                        });
+                    }
+                }
+                None => {
+                    if let Some(level) = parse_level(directive) {
+                        default = level;
+                    }
+                }
+            }
+        }
+
+        // Longest prefix first, so a more specific rule like
+        // `myapp::noisy` is checked before the broader `myapp`.
+        rules.sort_by(|a, b| b.target_prefix.len().cmp(&a.target_prefix.len()));
+
+        Filter {
+            rules,
+            default,
+            message_regex: regex,
+        }
+    }
+
+    /// Reads directives from the given environment variable, if set.
+    pub fn from_env(var: &str) -> Option<Self> {
+        std::env::var(var).ok().map(|spec| Filter::parse(&spec))
+    }
+
+    /// The most permissive level this filter could let through, across the
+    /// default and every rule. Used to set `log::set_max_level` so the
+    /// `log` crate's own fast-path doesn't discard records before this
+    /// filter gets a chance to evaluate per-target rules.
+    pub fn max_level(&self) -> LevelFilter {
+        self.rules
+            .iter()
+            .map(|rule| rule.level)
+            .fold(self.default, LevelFilter::max)
+    }
+
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.rules
+            .iter()
+            .find(|rule| target.starts_with(rule.target_prefix.as_str()))
+            .map(|rule| rule.level)
+            .unwrap_or(self.default)
+    }
+
+    pub fn enabled(&self, target: &str, level: Level) -> bool {
+        level.to_level_filter() <= self.level_for(target)
+    }
+
+    /// Additional filter applied to the rendered message text, if a regex
+    /// was supplied. Records are allowed through when there's no regex.
+    pub fn message_allowed(&self, message: &str) -> bool {
+        self.message_regex
+            .as_ref()
+            .map(|regex| regex.is_match(message))
+            .unwrap_or(true)
+    }
+}
+
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.trim().to_ascii_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bare_directive_sets_the_default() {
+        let filter = Filter::parse("debug");
+        assert_eq!(filter.level_for("anything::at::all"), LevelFilter::Debug);
+    }
+
+    #[test]
+    fn unset_default_falls_back_to_info() {
+        let filter = Filter::parse("myapp=trace");
+        assert_eq!(filter.level_for("someother::crate"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn longest_prefix_wins_over_broader_rule() {
+        let filter = Filter::parse("myapp=debug,myapp::noisy=warn");
+        assert_eq!(filter.level_for("myapp::noisy::submodule"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("myapp::other"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("unrelated"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn off_directive_disables_everything() {
+        let filter = Filter::parse("off");
+        assert!(!filter.enabled("myapp", Level::Error));
+        assert!(!filter.enabled("myapp", Level::Trace));
+    }
+
+    #[test]
+    fn enabled_respects_level_ordering() {
+        let filter = Filter::parse("myapp=warn");
+        assert!(filter.enabled("myapp", Level::Error));
+        assert!(filter.enabled("myapp", Level::Warn));
+        assert!(!filter.enabled("myapp", Level::Info));
+    }
+
+    #[test]
+    fn trailing_regex_gates_on_message_text() {
+        let filter = Filter::parse("debug/fail");
+        assert!(filter.message_allowed("request failed"));
+        assert!(!filter.message_allowed("request ok"));
+    }
+
+    #[test]
+    fn no_regex_allows_every_message() {
+        let filter = Filter::parse("debug");
+        assert!(filter.message_allowed("anything"));
+    }
+
+    #[test]
+    fn max_level_is_the_most_permissive_of_default_and_rules() {
+        let filter = Filter::parse("warn,myapp::noisy=trace");
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn from_env_parses_the_named_variable_and_is_none_when_unset() {
+        // Scoped to one test so no other test races on the same variable.
+        std::env::remove_var("LOG_SEQ_TEST_FILTER");
+        assert!(Filter::from_env("LOG_SEQ_TEST_FILTER").is_none());
+
+        std::env::set_var("LOG_SEQ_TEST_FILTER", "myapp=debug");
+        let filter = Filter::from_env("LOG_SEQ_TEST_FILTER").expect("env var was set");
+        assert_eq!(filter.level_for("myapp::x"), LevelFilter::Debug);
+
+        std::env::remove_var("LOG_SEQ_TEST_FILTER");
+    }
+}
@@ -1,5 +1,34 @@
+mod filter;
+mod ring;
+mod sink;
+mod spool;
+mod worker;
+
 use chrono::Utc;
+use filter::Filter;
+use log::kv;
 use log::{Level, LevelFilter, Log, Metadata, Record};
+pub use ring::Query;
+use ring::RingBuffer;
+pub use sink::default_format;
+use sink::{ConsoleSink, FormatFn, Sink, SeqIngestSink, WriterSink};
+use spool::Spool;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use worker::{SeqWorker, WorkerMessage};
+
+const DEFAULT_BATCH_SIZE: usize = 100;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+const DEFAULT_CHANNEL_CAPACITY: usize = 4096;
+
+/// Environment variable read by `Seq::init` for filter directives. When
+/// set, it takes precedence over directives passed to `with_filter`.
+const FILTER_ENV_VAR: &str = "SEQ_LOG";
 
 pub struct Seq {
     default_level: LevelFilter,
@@ -7,6 +36,28 @@ pub struct Seq {
     api_key: String,
     application: String,
     module: String,
+    batch_size: usize,
+    flush_interval: Duration,
+    channel_capacity: usize,
+    durable_buffer: Option<(PathBuf, u64)>,
+    filter_directives: Option<String>,
+    ring_buffer_capacity: Option<usize>,
+    extra_sinks: Vec<ExtraSinkConfig>,
+}
+
+/// A sink attached via `with_console_sink`/`with_writer_sink`, still
+/// carrying its config (not yet turned into a `Box<dyn Sink>`) until
+/// `init` builds the real sink list.
+enum ExtraSinkConfig {
+    Console {
+        level: LevelFilter,
+        format: FormatFn,
+    },
+    Writer {
+        level: LevelFilter,
+        format: FormatFn,
+        writer: Box<dyn Write + Send>,
+    },
 }
 
 impl Seq {
@@ -17,12 +68,167 @@ impl Seq {
             api_key: api_key.to_string(),
             application: application.to_string(),
             module: module.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            durable_buffer: None,
+            filter_directives: None,
+            ring_buffer_capacity: None,
+            extra_sinks: Vec::new(),
         }
     }
 
-    pub fn init(self) {
-        log::set_max_level(self.default_level);
-        log::set_boxed_logger(Box::new(self)).expect("Unable to set seq as a logger");
+    /// Number of buffered events that triggers an immediate POST instead of
+    /// waiting for the flush interval.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Maximum time a record waits in the buffer before being sent.
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    /// The level at which the fallback filter (used when neither the
+    /// `SEQ_LOG` environment variable nor `with_filter` is set) lets through
+    /// records whose target starts with `module`. In that fallback, records
+    /// from other targets pass only at `Warn` or above, whatever this is set
+    /// to. Raise this (or use `with_filter`/`SEQ_LOG`) to ingest
+    /// `Debug`/`Trace` records into Seq.
+    pub fn with_default_level(mut self, default_level: LevelFilter) -> Self {
+        self.default_level = default_level;
+        self
+    }
+
+    /// Capacity of the bounded channel between callers and the worker
+    /// thread. Once full, `log` drops records rather than blocking the
+    /// caller; dropped events are counted and available via
+    /// `SeqHandle::dropped_count`.
+    pub fn with_channel_capacity(mut self, channel_capacity: usize) -> Self {
+        self.channel_capacity = channel_capacity;
+        self
+    }
+
+    /// Spools batches that fail to send under `path` instead of dropping
+    /// them, retrying with backoff once the worker notices Seq is
+    /// reachable again. `max_bytes` bounds the spool directory; once
+    /// exceeded, the oldest segment is dropped with a logged warning.
+    pub fn with_durable_buffer(mut self, path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        self.durable_buffer = Some((path.into(), max_bytes));
+        self
+    }
+
+    /// Overrides the fallback module-prefix gate with `RUST_LOG`-style
+    /// directives, e.g. `"myapp=debug,myapp::noisy=warn,off"`. Directives
+    /// from the `SEQ_LOG` environment variable take precedence over this at
+    /// `init` time, so deployments can override verbosity without a
+    /// redeploy.
+    pub fn with_filter(mut self, directives: &str) -> Self {
+        self.filter_directives = Some(directives.to_string());
+        self
+    }
+
+    /// Retains the most recent `capacity` events in memory, queryable via
+    /// `SeqHandle::query`, independent of whether they reached the Seq
+    /// server.
+    pub fn with_ring_buffer(mut self, capacity: usize) -> Self {
+        self.ring_buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Attaches a colorized terminal sink at the given level, using
+    /// `format` to render each record. Color is auto-disabled when stdout
+    /// isn't a TTY. Pass `log_seq::default_format` for the crate's
+    /// original `[ LEVEL ] message` style.
+    pub fn with_console_sink(mut self, level: LevelFilter, format: impl Fn(&Record) -> String + Send + Sync + 'static) -> Self {
+        self.extra_sinks.push(ExtraSinkConfig::Console {
+            level,
+            format: Arc::new(format),
+        });
+        self
+    }
+
+    /// Attaches a plain, non-colorized sink writing to any `io::Write`
+    /// (a file, a pipe, an in-memory buffer for tests), at the given level.
+    pub fn with_writer_sink(
+        mut self,
+        level: LevelFilter,
+        writer: impl Write + Send + 'static,
+        format: impl Fn(&Record) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.extra_sinks.push(ExtraSinkConfig::Writer {
+            level,
+            format: Arc::new(format),
+            writer: Box::new(writer),
+        });
+        self
+    }
+
+    /// Spawns the background worker thread, installs this as the global
+    /// `log` logger, and returns a handle for flushing and inspecting drop
+    /// counts.
+    pub fn init(self) -> SeqHandle {
+        let filter = Filter::from_env(FILTER_ENV_VAR)
+            .or_else(|| self.filter_directives.as_deref().map(Filter::parse))
+            .unwrap_or_else(|| Filter::default_for_module(&self.module, self.default_level));
+        log::set_max_level(filter.max_level());
+
+        let (sender, receiver) = mpsc::sync_channel(self.channel_capacity);
+
+        let spool = self.durable_buffer.map(|(path, max_bytes)| {
+            Spool::new(path, max_bytes).expect("Unable to create seq durable buffer directory")
+        });
+
+        let worker = SeqWorker {
+            ingest_url: self.ingest_url,
+            api_key: self.api_key,
+            receiver,
+            batch_size: self.batch_size,
+            flush_interval: self.flush_interval,
+            spool,
+        };
+        thread::Builder::new()
+            .name("seq-worker".to_string())
+            .spawn(move || worker.run())
+            .expect("Unable to spawn seq worker thread");
+
+        let dropped = Arc::new(AtomicU64::new(0));
+        let ring_buffer = self.ring_buffer_capacity.map(|capacity| Arc::new(RingBuffer::new(capacity)));
+
+        let mut sinks: Vec<Box<dyn Sink>> = Vec::with_capacity(1 + self.extra_sinks.len());
+        sinks.push(Box::new(SeqIngestSink {
+            // The `Filter` is the single source of truth for what reaches
+            // Seq; capping this sink's own level independently would
+            // silently drop anything `with_filter`/`SEQ_LOG` allows through
+            // above `Info` (e.g. per-module `debug`/`trace` directives).
+            level: LevelFilter::Trace,
+            application: self.application,
+            sender: sender.clone(),
+            dropped: dropped.clone(),
+            ring_buffer: ring_buffer.clone(),
+        }));
+        for extra in self.extra_sinks {
+            let sink: Box<dyn Sink> = match extra {
+                ExtraSinkConfig::Console { level, format } => Box::new(ConsoleSink::new(level, format)),
+                ExtraSinkConfig::Writer { level, format, writer } => Box::new(WriterSink {
+                    level,
+                    format,
+                    writer: Mutex::new(writer),
+                }),
+            };
+            sinks.push(sink);
+        }
+
+        let logger = SeqLogger { filter, sinks };
+        log::set_boxed_logger(Box::new(logger)).expect("Unable to set seq as a logger");
+
+        SeqHandle {
+            sender,
+            dropped,
+            ring_buffer,
+        }
     }
 
     fn level_to_seq_level(level: &Level) -> String {
@@ -34,60 +240,165 @@ impl Seq {
             Level::Error => String::from("Error"),
         }
     }
+}
 
-    fn debug_print(record: &Record) {
-        let prefix = match record.level() {
-            Level::Trace => "[ TRACE ]",
-            Level::Debug => "[ DEBUG ]",
-            Level::Info => "[ INFO ]",
-            Level::Warn => "[ WARN ]",
-            Level::Error => "[ ERROR ]",
-        };
-        println!("{} {}", prefix, record.args().to_string().replace("\"", ""));
+/// Returned from `Seq::init`. Lets the embedding application force a flush
+/// (e.g. before exiting) and see how many events were dropped due to a full
+/// channel. Dropping the handle triggers a final blocking flush.
+pub struct SeqHandle {
+    sender: SyncSender<WorkerMessage>,
+    dropped: Arc<AtomicU64>,
+    ring_buffer: Option<Arc<RingBuffer>>,
+}
+
+impl SeqHandle {
+    /// Blocks until the worker has drained its current buffer.
+    pub fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::sync_channel(0);
+        if self.sender.send(WorkerMessage::Flush(ack_tx)).is_ok() {
+            let _ = ack_rx.recv();
+        }
+    }
+
+    /// Number of events dropped so far because the channel to the worker
+    /// was full.
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Searches the in-memory ring buffer, if `Seq::with_ring_buffer` was
+    /// configured. Returns an empty list otherwise.
+    pub fn query(&self, query: &Query) -> Vec<SeqMessage> {
+        match &self.ring_buffer {
+            Some(ring_buffer) => ring_buffer.query(query),
+            None => Vec::new(),
+        }
     }
 }
 
+impl Drop for SeqHandle {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+/// The `log::Log` implementation installed by `Seq::init`. Holds the
+/// global target/level filter plus every attached `Sink` (Seq ingest is
+/// always sinks[0]; console and writer sinks follow in attachment order),
+/// and fans each accepted record out to all of them.
+struct SeqLogger {
+    filter: Filter,
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+/// A single captured log event, in the shape Seq ingests it. Also the type
+/// returned from `SeqHandle::query` against the in-memory ring buffer, so
+/// an embedding application can inspect recent diagnostics without a round
+/// trip to the Seq server.
 #[derive(Debug, Clone)]
-struct SeqMessage {
-    timestamp: String,
-    message: String,
-    application: String,
-    line: u32,
-    level: String,
-    module: String,
-    file: String,
+pub struct SeqMessage {
+    pub timestamp: chrono::DateTime<Utc>,
+    pub message: String,
+    pub application: String,
+    pub line: u32,
+    pub level: Level,
+    pub module: String,
+    pub file: String,
+    /// Structured key-value pairs captured from `record.key_values()`, kept
+    /// as `serde_json::Value` so numbers/bools round-trip without quoting.
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Walks a `log::kv::Source` and collects every pair into a JSON map,
+/// preserving the value's native type instead of flattening everything to
+/// a string.
+struct PropertyVisitor<'a> {
+    properties: &'a mut serde_json::Map<String, serde_json::Value>,
+}
+
+impl<'a, 'kvs> kv::VisitSource<'kvs> for PropertyVisitor<'a> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        // Cast through `Value`'s own typed accessors rather than
+        // `serde_json::to_value(&value)`: the latter needs `log::kv::Value`
+        // to implement `Serialize`, which only exists when `log` is built
+        // with its (separate, non-default) serde-kv feature. The accessors
+        // below are part of the base `kv` API, so this works regardless.
+        let json_value = if let Some(v) = value.to_bool() {
+            serde_json::Value::Bool(v)
+        } else if let Some(v) = value.to_u64() {
+            serde_json::Value::Number(v.into())
+        } else if let Some(v) = value.to_i64() {
+            serde_json::Value::Number(v.into())
+        } else if let Some(v) = value.to_f64().and_then(serde_json::Number::from_f64) {
+            serde_json::Value::Number(v)
+        } else if let Some(v) = value.to_borrowed_str() {
+            serde_json::Value::String(v.to_string())
+        } else {
+            serde_json::Value::String(value.to_string())
+        };
+        self.properties.insert(key.to_string(), json_value);
+        Ok(())
+    }
 }
 
 impl SeqMessage {
-    fn from_record(seq: &Seq, record: &Record) -> Self {
+    pub(crate) fn from_record(application: &str, record: &Record) -> Self {
+        let mut properties = serde_json::Map::new();
+        let mut visitor = PropertyVisitor {
+            properties: &mut properties,
+        };
+        let _ = record.key_values().visit(&mut visitor);
+
         SeqMessage {
-            timestamp: Utc::now().format("%+").to_string(),
+            timestamp: Utc::now(),
             message: record.args().to_string(),
-            application: seq.application.clone(),
+            application: application.to_string(),
             line: record.line().unwrap_or(0),
-            level: Seq::level_to_seq_level(&record.level()),
+            level: record.level(),
             module: record.module_path().unwrap_or("").to_string(),
             file: record.file().unwrap_or("").to_string(),
+            properties,
         }
     }
 
-    fn as_clef(&self) -> String {
-        format!(
-            "{{\"@t\": \"{}\", \"@mt\": \"{}\", \"Application\": \"{}\", \"Line\": \"{}\", \"@l\": \"{}\", \"Module\": \"{}\", \"file\": \"{}\"}}",
-            self.timestamp,
-            self.message.replace("\"", "\\\"").replace("\n", "\\n"),
-            self.application,
-            self.line,
-            self.level,
-            self.module,
-            self.file
-        )
+    /// Renders this message as a single line of CLEF (Compact Log Event
+    /// Format). `@mt` is named after Serilog's message template field, but
+    /// it only ever carries the already-rendered message: `log`'s macros
+    /// substitute named holes into `record.args()` at the `format_args!`
+    /// call site, before the `Record` reaches this crate at all, so there
+    /// is no raw template left to capture here. Properties are still
+    /// attached alongside it, they just aren't referenced from `@mt` by
+    /// name the way a true Serilog template's would be.
+    pub(crate) fn as_clef(&self) -> String {
+        let mut map = serde_json::Map::new();
+        map.insert(
+            "@t".to_string(),
+            serde_json::Value::String(self.timestamp.format("%+").to_string()),
+        );
+        map.insert("@mt".to_string(), serde_json::Value::String(self.message.clone()));
+        map.insert(
+            "Application".to_string(),
+            serde_json::Value::String(self.application.clone()),
+        );
+        map.insert("Line".to_string(), serde_json::Value::from(self.line));
+        map.insert(
+            "@l".to_string(),
+            serde_json::Value::String(Seq::level_to_seq_level(&self.level)),
+        );
+        map.insert("Module".to_string(), serde_json::Value::String(self.module.clone()));
+        map.insert("file".to_string(), serde_json::Value::String(self.file.clone()));
+
+        for (key, value) in &self.properties {
+            map.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+
+        serde_json::to_string(&map).unwrap_or_default()
     }
 }
 
-impl Log for Seq {
+impl Log for SeqLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level().to_level_filter() <= self.default_level
+        self.filter.enabled(metadata.target(), metadata.level()) && self.sinks.iter().any(|sink| sink.enabled(metadata))
     }
 
     fn log(&self, record: &Record) {
@@ -95,44 +406,100 @@ impl Log for Seq {
             return;
         }
 
-        if !record
-            .module_path()
-            .unwrap_or("")
-            .contains(self.module.as_str())
-            && !(record.metadata().level().to_level_filter() <= LevelFilter::Warn)
-        {
+        if !self.filter.message_allowed(&record.args().to_string()) {
             return;
         }
 
-        Seq::debug_print(&record);
-        let msg = SeqMessage::from_record(self, &record);
-
-        let ingest_url = format!("{}/api/events/raw?clef", self.ingest_url);
-        match ureq::post(ingest_url.as_str())
-            .header("X-Seq-ApiKey", &self.api_key)
-            .header("Content-Type", "application/vnd.serilog.clef")
-            .send(msg.as_clef())
-        {
-            Ok(_) => {}
-            Err(why) => {
-                eprintln!("Seq msg attempt: {:#?}", msg);
-                eprintln!("Rendered message: {}", msg.as_clef());
-                eprintln!("Updating seq logs failed: {:?}", why);
+        for sink in &self.sinks {
+            if sink.enabled(record.metadata()) {
+                sink.dispatch(record);
             }
         }
     }
 
-    fn flush(&self) {}
+    fn flush(&self) {
+        for sink in &self.sinks {
+            sink.flush();
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Seq;
+    use super::{Seq, SeqMessage};
+    use chrono::{TimeZone, Utc};
+    use log::Level;
 
     #[test]
     fn basics() {
-        Seq::new("", "", "log_seq test", "log_seq").init();
+        let _handle = Seq::new("", "", "log_seq test", "log_seq").init();
         log::warn!("test test");
         log::error!("Testing an error code");
+        log::info!(user_id = 42, action = "login"; "user logged in");
+    }
+
+    fn sample_message() -> SeqMessage {
+        SeqMessage {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap(),
+            message: "user logged in".to_string(),
+            application: "log_seq test".to_string(),
+            line: 42,
+            level: Level::Info,
+            module: "myapp::auth".to_string(),
+            file: "src/auth.rs".to_string(),
+            properties: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn as_clef_encodes_reserved_fields() {
+        let msg = sample_message();
+        let encoded = msg.as_clef();
+        let value: serde_json::Value = serde_json::from_str(&encoded).expect("valid JSON line");
+
+        assert_eq!(value["@t"], "2024-01-02T03:04:05+00:00");
+        assert_eq!(value["@mt"], "user logged in");
+        assert_eq!(value["@l"], "Information");
+        assert_eq!(value["Application"], "log_seq test");
+        assert_eq!(value["Module"], "myapp::auth");
+        assert_eq!(value["Line"], 42);
+    }
+
+    #[test]
+    fn as_clef_preserves_property_types_without_quoting_non_strings() {
+        let mut msg = sample_message();
+        msg.properties.insert("user_id".to_string(), serde_json::Value::from(42));
+        msg.properties.insert("is_retry".to_string(), serde_json::Value::Bool(false));
+        msg.properties.insert("action".to_string(), serde_json::Value::String("login".to_string()));
+
+        let value: serde_json::Value = serde_json::from_str(&msg.as_clef()).expect("valid JSON line");
+
+        assert_eq!(value["user_id"], 42);
+        assert_eq!(value["is_retry"], false);
+        assert_eq!(value["action"], "login");
+    }
+
+    #[test]
+    fn as_clef_escapes_special_characters_in_strings() {
+        let mut msg = sample_message();
+        msg.message = "line one\nline \"two\"".to_string();
+
+        let encoded = msg.as_clef();
+        // The raw encoded line must escape the newline and quotes rather than
+        // breaking the single-line-per-event CLEF format.
+        assert!(!encoded.contains('\n'));
+        assert!(encoded.contains("line one\\nline \\\"two\\\""));
+
+        let value: serde_json::Value = serde_json::from_str(&encoded).expect("valid JSON line");
+        assert_eq!(value["@mt"], "line one\nline \"two\"");
+    }
+
+    #[test]
+    fn as_clef_does_not_let_a_property_clobber_a_reserved_field() {
+        let mut msg = sample_message();
+        msg.properties.insert("@mt".to_string(), serde_json::Value::String("forged".to_string()));
+
+        let value: serde_json::Value = serde_json::from_str(&msg.as_clef()).expect("valid JSON line");
+        assert_eq!(value["@mt"], "user logged in");
     }
 }
@@ -0,0 +1,207 @@
+use crate::SeqMessage;
+use chrono::{DateTime, Utc};
+use log::Level;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Bounded in-memory history of recently logged events, so an embedding
+/// application can inspect diagnostics without round-tripping to the Seq
+/// server (or at all, if the ingest URL is unreachable).
+pub(crate) struct RingBuffer {
+    capacity: usize,
+    events: Mutex<VecDeque<SeqMessage>>,
+}
+
+impl RingBuffer {
+    pub fn new(capacity: usize) -> Self {
+        RingBuffer {
+            capacity,
+            events: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub fn push(&self, event: SeqMessage) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut events = self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    /// Returns retained events matching `query`, oldest first.
+    pub fn query(&self, query: &Query) -> Vec<SeqMessage> {
+        let events = self.events.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let matches = events.iter().filter(|event| query.matches(event)).cloned();
+
+        match query.limit {
+            Some(limit) => matches.collect::<Vec<_>>().into_iter().rev().take(limit).rev().collect(),
+            None => matches.collect(),
+        }
+    }
+}
+
+/// Filter used to search the ring buffer. Build with the `with_*` methods;
+/// any field left unset matches everything.
+#[derive(Default)]
+pub struct Query {
+    min_level: Option<Level>,
+    target: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    message_regex: Option<Regex>,
+    limit: Option<usize>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// Only events at this level or more severe (`Error` is most severe).
+    pub fn with_min_level(mut self, min_level: Level) -> Self {
+        self.min_level = Some(min_level);
+        self
+    }
+
+    /// Only events whose module path starts with this prefix.
+    pub fn with_target(mut self, target: impl Into<String>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    /// Only events logged at or after this time.
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    /// Only events logged at or before this time.
+    pub fn with_until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Only events whose rendered message matches this regex.
+    pub fn with_message_regex(mut self, regex: Regex) -> Self {
+        self.message_regex = Some(regex);
+        self
+    }
+
+    /// Caps the number of events returned, keeping the most recent ones.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    fn matches(&self, event: &SeqMessage) -> bool {
+        if let Some(min_level) = self.min_level {
+            if event.level > min_level {
+                return false;
+            }
+        }
+        if let Some(target) = &self.target {
+            if !event.module.starts_with(target.as_str()) {
+                return false;
+            }
+        }
+        if let Some(since) = self.since {
+            if event.timestamp < since {
+                return false;
+            }
+        }
+        if let Some(until) = self.until {
+            if event.timestamp > until {
+                return false;
+            }
+        }
+        if let Some(regex) = &self.message_regex {
+            if !regex.is_match(&event.message) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Query, RingBuffer};
+    use crate::SeqMessage;
+    use chrono::{TimeZone, Utc};
+    use log::Level;
+
+    fn event(level: Level, module: &str, message: &str) -> SeqMessage {
+        SeqMessage {
+            timestamp: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            message: message.to_string(),
+            application: "log_seq test".to_string(),
+            line: 1,
+            level,
+            module: module.to_string(),
+            file: "src/lib.rs".to_string(),
+            properties: serde_json::Map::new(),
+        }
+    }
+
+    #[test]
+    fn push_evicts_oldest_once_capacity_is_reached() {
+        let buffer = RingBuffer::new(2);
+        buffer.push(event(Level::Info, "myapp", "one"));
+        buffer.push(event(Level::Info, "myapp", "two"));
+        buffer.push(event(Level::Info, "myapp", "three"));
+
+        let all = buffer.query(&Query::new());
+        let messages: Vec<&str> = all.iter().map(|event| event.message.as_str()).collect();
+        assert_eq!(messages, vec!["two", "three"]);
+    }
+
+    #[test]
+    fn query_filters_by_min_level() {
+        let buffer = RingBuffer::new(10);
+        buffer.push(event(Level::Trace, "myapp", "trace event"));
+        buffer.push(event(Level::Error, "myapp", "error event"));
+
+        let results = buffer.query(&Query::new().with_min_level(Level::Warn));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "error event");
+    }
+
+    #[test]
+    fn query_filters_by_target_prefix() {
+        let buffer = RingBuffer::new(10);
+        buffer.push(event(Level::Info, "myapp::auth", "auth event"));
+        buffer.push(event(Level::Info, "myapp::db", "db event"));
+
+        let results = buffer.query(&Query::new().with_target("myapp::auth"));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "auth event");
+    }
+
+    #[test]
+    fn query_limit_keeps_the_most_recent_events() {
+        let buffer = RingBuffer::new(10);
+        buffer.push(event(Level::Info, "myapp", "one"));
+        buffer.push(event(Level::Info, "myapp", "two"));
+        buffer.push(event(Level::Info, "myapp", "three"));
+
+        let results = buffer.query(&Query::new().with_limit(2));
+        let messages: Vec<&str> = results.iter().map(|event| event.message.as_str()).collect();
+        assert_eq!(messages, vec!["two", "three"]);
+    }
+
+    #[test]
+    fn query_filters_by_message_regex() {
+        let buffer = RingBuffer::new(10);
+        buffer.push(event(Level::Info, "myapp", "request succeeded"));
+        buffer.push(event(Level::Info, "myapp", "request failed"));
+
+        let results = buffer.query(&Query::new().with_message_regex(regex::Regex::new("failed").unwrap()));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].message, "request failed");
+    }
+}
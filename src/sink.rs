@@ -0,0 +1,145 @@
+use crate::ring::RingBuffer;
+use crate::worker::WorkerMessage;
+use crate::SeqMessage;
+use log::{Level, LevelFilter, Metadata, Record};
+use std::io::{IsTerminal, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+
+/// A closure that renders a `Record` to the string a sink actually writes
+/// or sends. Held behind an `Arc` so `Seq`'s builder methods can take any
+/// closure, not just `fn` pointers.
+pub(crate) type FormatFn = Arc<dyn Fn(&Record) -> String + Send + Sync>;
+
+/// One destination a log record can be fanned out to. `Log::log` calls
+/// `dispatch` on every sink whose `enabled` returns true.
+pub(crate) trait Sink: Send + Sync {
+    fn enabled(&self, metadata: &Metadata) -> bool;
+    fn dispatch(&self, record: &Record);
+    /// Only the Seq ingest sink does anything meaningful here; console and
+    /// writer sinks write synchronously so there's nothing to drain.
+    fn flush(&self) {}
+}
+
+/// The crate's original destination: batches CLEF lines onto the worker
+/// thread's channel, and mirrors each event into the ring buffer if one is
+/// configured.
+pub(crate) struct SeqIngestSink {
+    pub level: LevelFilter,
+    pub application: String,
+    pub sender: SyncSender<WorkerMessage>,
+    pub dropped: Arc<AtomicU64>,
+    pub ring_buffer: Option<Arc<RingBuffer>>,
+}
+
+impl Sink for SeqIngestSink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level().to_level_filter() <= self.level
+    }
+
+    fn dispatch(&self, record: &Record) {
+        let msg = SeqMessage::from_record(&self.application, record);
+
+        if let Some(ring_buffer) = &self.ring_buffer {
+            ring_buffer.push(msg.clone());
+        }
+
+        match self.sender.try_send(WorkerMessage::Record(msg.as_clef())) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                eprintln!("Seq worker thread is gone, dropping log record: {:#?}", msg);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        let (ack_tx, ack_rx) = std::sync::mpsc::sync_channel(0);
+        if self.sender.send(WorkerMessage::Flush(ack_tx)).is_ok() {
+            let _ = ack_rx.recv();
+        }
+    }
+}
+
+/// Colorized terminal output. Color is auto-disabled when stdout isn't a
+/// TTY (piped to a file, captured by a test harness, etc.) so redirected
+/// output doesn't get full of ANSI escapes.
+pub(crate) struct ConsoleSink {
+    pub level: LevelFilter,
+    pub format: FormatFn,
+    pub color: bool,
+}
+
+impl ConsoleSink {
+    pub fn new(level: LevelFilter, format: FormatFn) -> Self {
+        ConsoleSink {
+            level,
+            format,
+            color: std::io::stdout().is_terminal(),
+        }
+    }
+
+    fn ansi_color(level: Level) -> &'static str {
+        match level {
+            Level::Trace => "\x1b[90m", // bright black
+            Level::Debug => "\x1b[36m", // cyan
+            Level::Info => "\x1b[32m",  // green
+            Level::Warn => "\x1b[33m",  // yellow
+            Level::Error => "\x1b[31m", // red
+        }
+    }
+}
+
+impl Sink for ConsoleSink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level().to_level_filter() <= self.level
+    }
+
+    fn dispatch(&self, record: &Record) {
+        let line = (self.format)(record);
+        if self.color {
+            println!("{}{}\x1b[0m", Self::ansi_color(record.level()), line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+/// Plain, non-colorized output to any `io::Write` (a file, a pipe, an
+/// in-memory buffer for tests). Never emits ANSI escapes, since the point
+/// of this sink is captured output that gets parsed or diffed later.
+pub(crate) struct WriterSink {
+    pub level: LevelFilter,
+    pub format: FormatFn,
+    pub writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Sink for WriterSink {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level().to_level_filter() <= self.level
+    }
+
+    fn dispatch(&self, record: &Record) {
+        let line = (self.format)(record);
+        let mut writer = self.writer.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writeln!(writer, "{}", line);
+    }
+}
+
+/// The default format used when a sink is attached without a custom
+/// closure: `[ LEVEL ] message`, with the same level prefixes as the
+/// crate's original `debug_print` output. Double quotes in the message are
+/// kept as they are, not stripped.
+pub fn default_format(record: &Record) -> String {
+    let prefix = match record.level() {
+        Level::Trace => "[ TRACE ]",
+        Level::Debug => "[ DEBUG ]",
+        Level::Info => "[ INFO ]",
+        Level::Warn => "[ WARN ]",
+        Level::Error => "[ ERROR ]",
+    };
+    format!("{} {}", prefix, record.args())
+}
@@ -0,0 +1,162 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static SEGMENT_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A rolling on-disk backlog of CLEF batches that failed to send. Used by
+/// the worker to survive a Seq outage without losing events: failed
+/// batches are appended here as segment files and retried later with
+/// backoff, oldest first.
+pub(crate) struct Spool {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl Spool {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Spool { dir, max_bytes })
+    }
+
+    /// Appends a failed batch as a new segment file, then evicts the
+    /// oldest segments until the spool is back under `max_bytes`.
+    pub fn write_segment(&self, body: &str) -> io::Result<()> {
+        let name = format!(
+            "{}-{}.clef",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_nanos(),
+            SEGMENT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut file = fs::File::create(self.dir.join(name))?;
+        file.write_all(body.as_bytes())?;
+
+        self.enforce_cap();
+        Ok(())
+    }
+
+    /// Segment file paths, oldest first.
+    pub fn segments(&self) -> Vec<PathBuf> {
+        let mut segments: Vec<PathBuf> = fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map(|ext| ext == "clef").unwrap_or(false))
+            .collect();
+        segments.sort();
+        segments
+    }
+
+    pub fn read_segment(path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    pub fn remove_segment(path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn enforce_cap(&self) {
+        let segments = self.segments();
+        let mut total: u64 = segments
+            .iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+
+        for path in segments {
+            if total <= self.max_bytes {
+                break;
+            }
+            let size = fs::metadata(&path).map(|metadata| metadata.len()).unwrap_or(0);
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    total = total.saturating_sub(size);
+                    eprintln!(
+                        "Seq spool exceeded {} bytes, dropping oldest segment {}",
+                        self.max_bytes,
+                        path.display()
+                    );
+                }
+                Err(why) => {
+                    eprintln!("Seq spool cleanup failed to remove {}: {:?}", path.display(), why);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Spool;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static TEST_DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    /// A spool directory under the system temp dir that's removed when the
+    /// guard drops, so tests don't leak files into each other or across runs.
+    struct TempSpoolDir(std::path::PathBuf);
+
+    impl TempSpoolDir {
+        fn new() -> Self {
+            let id = TEST_DIR_COUNTER.fetch_add(1, Ordering::Relaxed);
+            let dir = std::env::temp_dir().join(format!("log_seq_spool_test_{}_{}", std::process::id(), id));
+            TempSpoolDir(dir)
+        }
+    }
+
+    impl Drop for TempSpoolDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn segments_are_ordered_oldest_first() {
+        let dir = TempSpoolDir::new();
+        let spool = Spool::new(dir.0.clone(), u64::MAX).unwrap();
+
+        spool.write_segment("first").unwrap();
+        spool.write_segment("second").unwrap();
+        spool.write_segment("third").unwrap();
+
+        let segments = spool.segments();
+        let bodies: Vec<String> = segments.iter().map(|path| Spool::read_segment(path).unwrap()).collect();
+        assert_eq!(bodies, vec!["first", "second", "third"]);
+    }
+
+    #[test]
+    fn enforce_cap_evicts_oldest_segments_first() {
+        let dir = TempSpoolDir::new();
+        // Each segment body is 5 bytes; cap the spool at 6 bytes so only
+        // the most recent one can survive.
+        let spool = Spool::new(dir.0.clone(), 6).unwrap();
+
+        spool.write_segment("aaaaa").unwrap();
+        spool.write_segment("bbbbb").unwrap();
+        spool.write_segment("ccccc").unwrap();
+
+        let remaining: Vec<String> = spool.segments().iter().map(|path| Spool::read_segment(path).unwrap()).collect();
+
+        assert_eq!(remaining, vec!["ccccc"]);
+    }
+
+    #[test]
+    fn read_and_remove_segment_round_trip() {
+        let dir = TempSpoolDir::new();
+        let spool = Spool::new(dir.0.clone(), u64::MAX).unwrap();
+
+        spool.write_segment("payload").unwrap();
+        let segments = spool.segments();
+        assert_eq!(segments.len(), 1);
+
+        assert_eq!(Spool::read_segment(&segments[0]).unwrap(), "payload");
+        Spool::remove_segment(&segments[0]).unwrap();
+        assert!(spool.segments().is_empty());
+    }
+}
@@ -0,0 +1,139 @@
+use crate::spool::Spool;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender};
+use std::time::{Duration, Instant};
+
+/// Messages sent from logging callers to the background worker thread.
+pub(crate) enum WorkerMessage {
+    /// A single CLEF-encoded event, ready to be appended to the next batch.
+    Record(String),
+    /// Drain whatever is buffered right now and acknowledge once sent.
+    Flush(SyncSender<()>),
+}
+
+const INITIAL_SPOOL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_SPOOL_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Owns the `ureq` agent and the batching/flush logic; runs on its own thread.
+pub(crate) struct SeqWorker {
+    pub ingest_url: String,
+    pub api_key: String,
+    pub receiver: Receiver<WorkerMessage>,
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    pub spool: Option<Spool>,
+}
+
+impl SeqWorker {
+    pub fn run(self) {
+        let agent = ureq::Agent::new_with_defaults();
+        let raw_url = format!("{}/api/events/raw?clef", self.ingest_url);
+        let mut buffer: Vec<String> = Vec::with_capacity(self.batch_size);
+        let mut deadline = Instant::now() + self.flush_interval;
+        let mut spool_backoff = INITIAL_SPOOL_BACKOFF;
+        let mut next_spool_retry = Instant::now();
+
+        loop {
+            let now = Instant::now();
+            let mut timeout = deadline.saturating_duration_since(now);
+            if self.spool.is_some() {
+                timeout = timeout.min(next_spool_retry.saturating_duration_since(now));
+            }
+
+            match self.receiver.recv_timeout(timeout) {
+                Ok(WorkerMessage::Record(clef)) => {
+                    buffer.push(clef);
+                    if buffer.len() >= self.batch_size {
+                        self.send_or_spool(&agent, &raw_url, &mut buffer);
+                        deadline = Instant::now() + self.flush_interval;
+                    }
+                }
+                Ok(WorkerMessage::Flush(ack)) => {
+                    self.send_or_spool(&agent, &raw_url, &mut buffer);
+                    deadline = Instant::now() + self.flush_interval;
+                    let _ = ack.send(());
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    self.send_or_spool(&agent, &raw_url, &mut buffer);
+                    deadline = Instant::now() + self.flush_interval;
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.send_or_spool(&agent, &raw_url, &mut buffer);
+                    break;
+                }
+            }
+
+            if Instant::now() >= next_spool_retry {
+                next_spool_retry = Instant::now()
+                    + self.retry_spool(&agent, &raw_url, &mut spool_backoff);
+            }
+        }
+    }
+
+    fn send_or_spool(&self, agent: &ureq::Agent, raw_url: &str, buffer: &mut Vec<String>) {
+        if buffer.is_empty() {
+            return;
+        }
+
+        let body = buffer.join("\n");
+        match Self::post(agent, raw_url, &self.api_key, &body) {
+            Ok(()) => {}
+            Err(why) => match &self.spool {
+                Some(spool) => {
+                    eprintln!("Updating seq logs failed, spooling batch: {:?}", why);
+                    if let Err(why) = spool.write_segment(&body) {
+                        eprintln!("Seq failed to write spool segment: {:?}", why);
+                    }
+                }
+                None => eprintln!("Updating seq logs failed, dropping batch: {:?}", why),
+            },
+        }
+
+        buffer.clear();
+    }
+
+    /// Replays spooled segments, oldest first, stopping at the first
+    /// failure so ordering is preserved. Returns how long to wait before
+    /// trying again: the backoff doubles (capped) on failure and resets
+    /// once every pending segment has been sent.
+    fn retry_spool(&self, agent: &ureq::Agent, raw_url: &str, backoff: &mut Duration) -> Duration {
+        let Some(spool) = &self.spool else {
+            return MAX_SPOOL_BACKOFF;
+        };
+
+        for path in spool.segments() {
+            let body = match Spool::read_segment(&path) {
+                Ok(body) => body,
+                Err(why) => {
+                    eprintln!("Seq failed to read spool segment {}: {:?}", path.display(), why);
+                    continue;
+                }
+            };
+
+            match Self::post(agent, raw_url, &self.api_key, &body) {
+                Ok(()) => {
+                    if let Err(why) = Spool::remove_segment(&path) {
+                        eprintln!("Seq failed to remove sent spool segment {}: {:?}", path.display(), why);
+                    }
+                }
+                Err(why) => {
+                    eprintln!("Seq spool retry failed for {}: {:?}", path.display(), why);
+                    let next = (*backoff * 2).min(MAX_SPOOL_BACKOFF);
+                    *backoff = next;
+                    return next;
+                }
+            }
+        }
+
+        *backoff = INITIAL_SPOOL_BACKOFF;
+        INITIAL_SPOOL_BACKOFF
+    }
+
+    fn post(agent: &ureq::Agent, raw_url: &str, api_key: &str, body: &str) -> Result<(), ureq::Error> {
+        agent
+            .post(raw_url)
+            .header("X-Seq-ApiKey", api_key)
+            .header("Content-Type", "application/vnd.serilog.clef")
+            .send(body.to_string())
+            .map(|_| ())
+    }
+}